@@ -1,18 +1,25 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt;
 use std::io;
-use std::io::prelude::*;
+use std::io::Write;
 use std::path;
-use std::str;
 
 use cargo::core::dependency::Kind;
 use cargo::core::package::PackageSet;
-use cargo::core::{Package, Resolve, Workspace};
+use cargo::core::{Package, PackageId, Resolve, Workspace};
 use cargo::ops;
 use cargo::util::Config;
 use cargo::CargoResult;
 use structopt::StructOpt;
 
+mod policy;
+mod reuse;
+mod spdx;
+mod writer;
+
+use policy::Policy;
+use writer::{Component, Format};
+
 #[derive(StructOpt)]
 #[structopt(bin_name = "cargo")]
 enum Opts {
@@ -26,6 +33,30 @@ struct Args {
     /// List all dependencies instead of only top level ones
     #[structopt(long = "all", short = "a")]
     all: bool,
+    /// Output format: text, json, spdx, cyclonedx
+    #[structopt(long = "format", value_name = "FORMAT", default_value = "text")]
+    format: Format,
+    /// Allow this SPDX license id under the policy check (repeatable)
+    #[structopt(long = "allow", value_name = "SPDX-ID")]
+    allow: Vec<String>,
+    /// Deny this SPDX license id under the policy check (repeatable)
+    #[structopt(long = "deny", value_name = "SPDX-ID")]
+    deny: Vec<String>,
+    /// Shortcut for `--allow`ing a built-in, non-exhaustive list of
+    /// OSI-approved licenses
+    #[structopt(long = "allow-osi-only")]
+    allow_osi_only: bool,
+    /// Treat packages with no resolvable license as policy violations
+    #[structopt(long = "deny-missing")]
+    deny_missing: bool,
+    /// Include build-dependencies in the top-level listing (--all always
+    /// includes every kind)
+    #[structopt(long = "include-build")]
+    include_build: bool,
+    /// Include dev-dependencies in the top-level listing (--all always
+    /// includes every kind)
+    #[structopt(long = "include-dev")]
+    include_dev: bool,
     /// Directory for all generated artifacts
     #[structopt(long = "target-dir", value_name = "DIRECTORY", parse(from_os_str))]
     target_dir: Option<path::PathBuf>,
@@ -83,83 +114,142 @@ fn real_main(config: &mut Config, args: Args) -> Result<(), Error> {
     let dependencies = if args.all {
         all_dependencies(&members, package_ids, resolve)?
     } else {
-        top_level_dependencies(&members, package_ids)?
+        top_level_dependencies(&members, package_ids, args.include_build, args.include_dev)?
     };
 
+    let policy = Policy::new(
+        &args.allow,
+        &args.deny,
+        args.allow_osi_only,
+        args.deny_missing,
+    );
+
     let mut packages = BTreeSet::new();
-    for package in &dependencies {
+    let mut violations = Vec::new();
+    for (package, kinds) in &dependencies {
         let name = package.name().to_owned();
         let version = format!("{}", package.version());
-        let licenses = format!("{}", package_licenses(package));
+        let parsed_licenses = package_licenses(package);
+        let licenses = format!("{}", parsed_licenses);
+        let license_expression = parsed_licenses.expression().map(|expr| expr.to_string());
         let license_files = package_license_files(package)?;
-        packages.insert((name, version, licenses, license_files));
+        let source = package_source(package);
+        let kinds: Vec<String> = kinds.iter().map(|kind| kind.to_string()).collect();
+
+        let mut notes = Vec::new();
+        // Only warn when Apache-2.0 is mandatory (a required `AND` term or
+        // the only satisfiable branch), not merely one `OR` alternative like
+        // the common `MIT OR Apache-2.0` dual license, which isn't obligated
+        // to carry an Apache-2.0 NOTICE at all.
+        let requires_apache2 = parsed_licenses.expression().map_or(false, |expression| {
+            expression.requires_license_id("Apache-2.0")
+        });
+        if requires_apache2 && !has_notice_file(&license_files) {
+            notes.push("declares Apache-2.0 but no NOTICE file was found".to_owned());
+        }
+
+        if policy.is_active() {
+            if let Err(reason) = policy.check(parsed_licenses.expression()) {
+                violations.push((name.clone(), version.clone(), reason));
+            }
+        }
+
+        packages.insert(Component {
+            name,
+            version,
+            licenses,
+            license_expression,
+            license_files,
+            notes,
+            source,
+            kinds,
+        });
     }
+    let packages: Vec<Component> = packages.into_iter().collect();
 
     let stdout = io::stdout();
     let mut out = stdout.lock();
 
-    {
-        let mut tw = tabwriter::TabWriter::new(&mut out);
-        writeln!(tw, "Name\t| Version\t| Licenses")?;
-        writeln!(tw, "----\t| -------\t| --------")?;
-        for (name, version, licenses, _) in &packages {
-            writeln!(tw, "{}\t| {}\t| {}", &name, &version, &licenses)?;
+    args.format.writer().write(&mut out, &packages)?;
+
+    if !violations.is_empty() {
+        let stderr = io::stderr();
+        let mut err_out = stderr.lock();
+        for (name, version, reason) in &violations {
+            writeln!(
+                err_out,
+                "license policy violation: {} {}: {}",
+                name, version, reason
+            )?;
         }
-
-        // TabWriter flush() makes the actual write to stdout.
-        tw.flush()?;
+        return Err(
+            failure::err_msg(format!("{} license policy violation(s)", violations.len())).into(),
+        );
     }
 
-    writeln!(out)?;
-    out.flush()?;
+    Ok(())
+}
 
-    for (name, version, _, license_files) in packages {
-        if license_files.is_empty() {
-            continue;
-        }
+/// The `Kind` a dependency enters the graph through, tracked per-package so
+/// the BoM can show e.g. a crate that is both a normal and a dev-dependency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DependencyKind {
+    Normal,
+    Build,
+    Development,
+}
 
-        writeln!(out, "-----BEGIN {} {} LICENSES-----", name, version)?;
-
-        let mut buf = Vec::new();
-        let mut licenses_to_print = license_files.len();
-        for file in license_files {
-            let mut fs = std::fs::File::open(file)?;
-            fs.read_to_end(&mut buf)?;
-            out.write_all(&buf)?;
-            buf.clear();
-            if licenses_to_print > 1 {
-                out.write_all(b"\n-----NEXT LICENSE-----\n")?;
-                licenses_to_print -= 1;
-            }
+impl From<Kind> for DependencyKind {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Normal => DependencyKind::Normal,
+            Kind::Build => DependencyKind::Build,
+            Kind::Development => DependencyKind::Development,
         }
+    }
+}
 
-        writeln!(out, "-----END {} {} LICENSES-----", name, version)?;
-        writeln!(out)?;
+impl fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyKind::Normal => write!(f, "normal"),
+            DependencyKind::Build => write!(f, "build"),
+            DependencyKind::Development => write!(f, "dev"),
+        }
     }
+}
 
-    out.flush()?;
-    Ok(())
+fn kind_enabled(kind: DependencyKind, include_build: bool, include_dev: bool) -> bool {
+    match kind {
+        DependencyKind::Normal => true,
+        DependencyKind::Build => include_build,
+        DependencyKind::Development => include_dev,
+    }
 }
 
 fn top_level_dependencies(
     members: &[Package],
     package_ids: PackageSet<'_>,
-) -> CargoResult<BTreeSet<Package>> {
-    let mut dependencies = BTreeSet::new();
+    include_build: bool,
+    include_dev: bool,
+) -> CargoResult<BTreeMap<Package, BTreeSet<DependencyKind>>> {
+    let mut dependencies: BTreeMap<Package, BTreeSet<DependencyKind>> = BTreeMap::new();
 
     for member in members {
         for dependency in member.dependencies() {
-            // Filter out Build and Development dependencies
-            match dependency.kind() {
-                Kind::Normal => (),
-                Kind::Build | Kind::Development => continue,
+            let kind = DependencyKind::from(dependency.kind());
+            if !kind_enabled(kind, include_build, include_dev) {
+                continue;
             }
             if let Some(dep) = package_ids
                 .package_ids()
                 .find(|id| dependency.matches_id(*id))
             {
                 let package = package_ids.get_one(dep)?;
-                dependencies.insert(package.to_owned());
+                dependencies
+                    .entry(package.to_owned())
+                    .or_insert_with(BTreeSet::new)
+                    .insert(kind);
             }
         }
     }
@@ -172,48 +262,109 @@ fn top_level_dependencies(
     Ok(dependencies)
 }
 
+/// Walks the full resolved graph reachable from the workspace members,
+/// tracking for each package the set of dependency kinds through which it
+/// was reached. `--all` has always listed the entire resolved graph
+/// (previously via `resolve.iter()`), build- and dev-dependencies included;
+/// `--include-build`/`--include-dev` only narrow the top-level listing, so
+/// every edge is queued here regardless of its kind and `kind_enabled` is
+/// not consulted.
 fn all_dependencies(
     members: &[Package],
     package_ids: PackageSet<'_>,
     resolve: Resolve,
-) -> CargoResult<BTreeSet<Package>> {
-    let mut dependencies = BTreeSet::new();
+) -> CargoResult<BTreeMap<Package, BTreeSet<DependencyKind>>> {
+    let mut dependencies: BTreeMap<Package, BTreeSet<DependencyKind>> = BTreeMap::new();
+    let mut queue: VecDeque<(PackageId, DependencyKind)> = VecDeque::new();
+
+    for member in members {
+        for (dep_id, deps) in resolve.deps(member.package_id()) {
+            for dep in deps {
+                queue.push_back((dep_id, DependencyKind::from(dep.kind())));
+            }
+        }
+    }
 
-    for package_id in resolve.iter() {
+    while let Some((package_id, kind)) = queue.pop_front() {
         let package = package_ids.get_one(package_id)?;
         if members.contains(&package) {
             // Skip listing our own packages in our workspace
             continue;
         }
-        dependencies.insert(package.to_owned());
+
+        let kinds = dependencies
+            .entry(package.to_owned())
+            .or_insert_with(BTreeSet::new);
+        if !kinds.insert(kind) {
+            // Already recorded this (package, kind); its transitive deps
+            // were already queued the first time we reached it this way.
+            continue;
+        }
+
+        for (dep_id, deps) in resolve.deps(package_id) {
+            for dep in deps {
+                queue.push_back((dep_id, DependencyKind::from(dep.kind())));
+            }
+        }
     }
 
     Ok(dependencies)
 }
 
+/// A short label for where a package's source code comes from: the
+/// registry, a git repository, or a local path.
+fn package_source(package: &Package) -> String {
+    let source_id = package.package_id().source_id();
+    if source_id.is_path() {
+        format!("path+{}", package.root().display())
+    } else if source_id.is_git() {
+        format!("git+{}", source_id.url())
+    } else {
+        format!("registry+{}", source_id.url())
+    }
+}
+
 fn package_licenses(package: &Package) -> Licenses<'_> {
     let metadata = package.manifest().metadata();
 
     if let Some(ref license_str) = metadata.license {
-        let licenses: BTreeSet<&str> = license_str
-            .split("OR")
-            .map(|s| s.split("AND"))
-            .flatten()
-            .map(|s| s.split('/'))
-            .flatten()
-            .map(str::trim)
-            .collect();
-        return Licenses::Licenses(licenses);
+        // `parse_expression` keeps unrecognized ids as ordinary leaves, so
+        // this only fails on a genuinely malformed expression (mismatched
+        // parens, a stray operator, a free-form string like "Apache License,
+        // Version 2.0", ...). That raw text isn't a valid SPDX expression,
+        // so it's kept as `Unstructured` rather than wrapped in a fake
+        // `Expression::License` leaf — machine-readable formats and the
+        // Apache-2.0 NOTICE check both need to tell the two apart.
+        return match spdx::parse_expression(license_str) {
+            Ok(expression) => Licenses::Expression(expression),
+            Err(_) => Licenses::Unstructured(license_str.trim().to_owned()),
+        };
     }
 
     if let Some(ref license_file) = metadata.license_file {
         return Licenses::File(license_file);
     }
 
+    if let Some(root) = package.manifest_path().parent() {
+        if let Some(expression) = reuse::scan_package(root) {
+            return Licenses::HeaderDerived(expression);
+        }
+    }
+
     Licenses::Missing
 }
 
-static LICENCE_FILE_NAMES: &[&str] = &["LICENSE", "UNLICENSE"];
+// `LICENCE` covers the British spelling used by some crates; `NOTICE` is the
+// file Apache-2.0 requires redistributors to carry alongside the license
+// text itself.
+static LICENCE_FILE_NAMES: &[&str] = &[
+    "LICENSE",
+    "LICENCE",
+    "UNLICENSE",
+    "NOTICE",
+    "COPYING",
+    "COPYRIGHT",
+];
 
 fn package_license_files(package: &Package) -> io::Result<Vec<path::PathBuf>> {
     let mut result = Vec::new();
@@ -222,8 +373,9 @@ fn package_license_files(package: &Package) -> io::Result<Vec<path::PathBuf>> {
         for entry in path.read_dir()? {
             if let Ok(entry) = entry {
                 if let Ok(name) = entry.file_name().into_string() {
+                    let upper = name.to_ascii_uppercase();
                     for license_name in LICENCE_FILE_NAMES {
-                        if name.starts_with(license_name) {
+                        if upper.starts_with(license_name) {
                             result.push(entry.path())
                         }
                     }
@@ -235,21 +387,52 @@ fn package_license_files(package: &Package) -> io::Result<Vec<path::PathBuf>> {
     Ok(result)
 }
 
+/// Whether `license_files` contains a `NOTICE` file, as Apache-2.0 requires
+/// redistributors to carry one alongside the license text.
+fn has_notice_file(license_files: &[path::PathBuf]) -> bool {
+    license_files.iter().any(|file| {
+        file.file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.to_ascii_uppercase().starts_with("NOTICE"))
+            .unwrap_or(false)
+    })
+}
+
 #[derive(Debug)]
 enum Licenses<'a> {
-    Licenses(BTreeSet<&'a str>),
+    Expression(spdx::Expression),
+    /// A license expression recovered from REUSE-style file headers rather
+    /// than the `Cargo.toml` manifest, see [`reuse::scan_package`].
+    HeaderDerived(spdx::Expression),
+    /// The raw `license` field, kept verbatim because it didn't parse as an
+    /// SPDX expression (e.g. `"Apache License, Version 2.0"`). Not a valid
+    /// SPDX expression, so it has no `.expression()` to offer.
+    Unstructured(String),
     File(&'a str),
     Missing,
 }
 
+impl<'a> Licenses<'a> {
+    /// The parsed expression backing this value, if it has one.
+    fn expression(&self) -> Option<&spdx::Expression> {
+        match self {
+            Licenses::Expression(expression) | Licenses::HeaderDerived(expression) => {
+                Some(expression)
+            }
+            Licenses::Unstructured(_) | Licenses::File(_) | Licenses::Missing => None,
+        }
+    }
+}
+
 impl<'a> fmt::Display for Licenses<'a> {
     fn fmt(self: &Self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        match *self {
+        match self {
             Licenses::File(_) => write!(f, "Specified in license file"),
             Licenses::Missing => write!(f, "Missing"),
-            Licenses::Licenses(ref lic_names) => {
-                let lics: Vec<String> = lic_names.iter().map(|s| String::from(*s)).collect();
-                write!(f, "{}", lics.join(", "))
+            Licenses::Expression(expression) => write!(f, "{}", expression),
+            Licenses::Unstructured(text) => write!(f, "{}", text),
+            Licenses::HeaderDerived(expression) => {
+                write!(f, "{} (from SPDX-License-Identifier headers)", expression)
             }
         }
     }
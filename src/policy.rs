@@ -0,0 +1,65 @@
+//! License allow/deny policy evaluation, used to gate CI on `--allow`/`--deny`.
+
+use std::collections::BTreeSet;
+
+use crate::spdx;
+
+pub struct Policy {
+    allow: BTreeSet<String>,
+    deny: BTreeSet<String>,
+    deny_missing: bool,
+}
+
+impl Policy {
+    pub fn new(
+        allow: &[String],
+        deny: &[String],
+        allow_osi_only: bool,
+        deny_missing: bool,
+    ) -> Self {
+        let mut allow: BTreeSet<String> = allow.iter().cloned().collect();
+        if allow_osi_only {
+            allow.extend(spdx::OSI_APPROVED_IDS.iter().map(|id| (*id).to_owned()));
+        }
+
+        Policy {
+            allow,
+            deny: deny.iter().cloned().collect(),
+            deny_missing,
+        }
+    }
+
+    /// Whether any policy flag was actually given. With none, every package
+    /// passes unconditionally and `check` should not be called.
+    pub fn is_active(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty() || self.deny_missing
+    }
+
+    /// Checks a package's parsed license expression against the policy.
+    /// `None` means the package had no license expression to evaluate: its
+    /// `license` field was missing, only pointed at a license file, or
+    /// didn't parse as a valid SPDX expression.
+    pub fn check(&self, expression: Option<&spdx::Expression>) -> Result<(), String> {
+        match expression {
+            Some(expression) => {
+                if expression.satisfied_by(&self.allow, &self.deny) {
+                    Ok(())
+                } else if self.allow.is_empty() {
+                    Err(format!("`{}` touches a --deny id", expression))
+                } else {
+                    Err(format!(
+                        "`{}` has no OR-branch fully covered by --allow (or touches a --deny id)",
+                        expression
+                    ))
+                }
+            }
+            None => {
+                if self.deny_missing {
+                    Err("license metadata is missing".to_owned())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
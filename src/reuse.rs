@@ -0,0 +1,108 @@
+//! Fallback license discovery for REUSE-compliant crates.
+//!
+//! [REUSE](https://reuse.software/) crates carry `SPDX-License-Identifier:`
+//! headers in per-file comments (or a `.reuse/dep5` manifest) instead of
+//! filling in the `Cargo.toml` `license` field. This is only consulted when
+//! [`crate::package_licenses`] would otherwise return `Licenses::Missing`.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crate::spdx;
+
+const HEADER_MARKER: &str = "SPDX-License-Identifier:";
+
+/// Scans a package's source tree for REUSE-style license metadata, trying
+/// the `.reuse/dep5` manifest first and falling back to per-file headers.
+pub fn scan_package(root: &Path) -> Option<spdx::Expression> {
+    scan_dep5(&root.join(".reuse").join("dep5")).or_else(|| scan_headers(root))
+}
+
+// DEP5 ("Machine-readable debian/copyright") stanzas are separated by blank
+// lines; we only care about each stanza's `License:` field.
+fn scan_dep5(path: &Path) -> Option<spdx::Expression> {
+    let contents = fs::read_to_string(path).ok()?;
+    let mut found = Vec::new();
+    for stanza in contents.split("\n\n") {
+        for line in stanza.lines() {
+            if let Some(rest) = line.strip_prefix("License:") {
+                if let Ok(expression) = spdx::parse_expression(rest.trim()) {
+                    found.push(expression);
+                }
+            }
+        }
+    }
+    union(found)
+}
+
+fn scan_headers(root: &Path) -> Option<spdx::Expression> {
+    let mut found = Vec::new();
+    walk(root, 0, &mut found);
+    union(found)
+}
+
+fn walk(dir: &Path, depth: u32, found: &mut Vec<spdx::Expression>) {
+    // Crate sources aren't deeply nested; this just guards against symlink cycles.
+    if depth > 16 {
+        return;
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if path.is_dir() {
+            if name == "target" || name == ".git" {
+                continue;
+            }
+            walk(&path, depth + 1, found);
+        } else if path.is_file() {
+            scan_file(&path, found);
+        }
+    }
+}
+
+fn scan_file(path: &Path, found: &mut Vec<spdx::Expression>) {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(_) => return,
+    };
+
+    let mut contents = String::new();
+    // Binary and non-UTF-8 files simply can't carry a text header; skip them.
+    if file.read_to_string(&mut contents).is_err() {
+        return;
+    }
+
+    for line in contents.lines() {
+        if let Some(index) = line.find(HEADER_MARKER) {
+            let rest = &line[index + HEADER_MARKER.len()..];
+            if let Ok(expression) = spdx::parse_expression(rest.trim()) {
+                found.push(expression);
+            }
+        }
+    }
+}
+
+/// Folds a list of discovered expressions into a single `OR`-joined
+/// expression, deduplicating by their canonical text.
+fn union(expressions: Vec<spdx::Expression>) -> Option<spdx::Expression> {
+    let mut seen = BTreeSet::new();
+    let mut unique = expressions
+        .into_iter()
+        .filter(|expression| seen.insert(expression.to_string()));
+
+    let mut result = unique.next()?;
+    for expression in unique {
+        result = spdx::Expression::Or(Box::new(result), Box::new(expression));
+    }
+    Some(result)
+}
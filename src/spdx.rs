@@ -0,0 +1,481 @@
+//! A small SPDX license-expression parser.
+//!
+//! This implements the subset of the SPDX license expression grammar
+//! (<https://spdx.github.io/spdx-spec/SPDX-license-expressions/>) that shows
+//! up in `Cargo.toml` `license` fields: bare license ids, the `AND`/`OR`/
+//! `WITH` operators, parentheses, the legacy `/` separator (normalized into
+//! `OR` while parsing), and the trailing `+` operator ("this version or
+//! later"), kept as-is on its license id.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A parsed SPDX license expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    /// A single license id, optionally combined with a `WITH <exception>` clause.
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+    /// `left AND right`
+    And(Box<Expression>, Box<Expression>),
+    /// `left OR right`
+    Or(Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    fn fmt_prec(&self, f: &mut fmt::Formatter<'_>, parent_prec: u8) -> fmt::Result {
+        match self {
+            Expression::License {
+                id,
+                exception: Some(exception),
+            } => write!(f, "{} WITH {}", id, exception),
+            Expression::License {
+                id,
+                exception: None,
+            } => write!(f, "{}", id),
+            Expression::And(left, right) => {
+                let needs_parens = parent_prec > 1;
+                if needs_parens {
+                    write!(f, "(")?;
+                }
+                left.fmt_prec(f, 1)?;
+                write!(f, " AND ")?;
+                right.fmt_prec(f, 1)?;
+                if needs_parens {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Expression::Or(left, right) => {
+                let needs_parens = parent_prec > 0;
+                if needs_parens {
+                    write!(f, "(")?;
+                }
+                left.fmt_prec(f, 0)?;
+                write!(f, " OR ")?;
+                right.fmt_prec(f, 0)?;
+                if needs_parens {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_prec(f, 0)
+    }
+}
+
+impl Expression {
+    /// Whether the given license id is mandatory under this expression —
+    /// every way of satisfying it must include `id` — rather than merely
+    /// mentioned as one alternative among others. An `AND` term is mandatory
+    /// if either side is; an `OR` term is mandatory only if *both* sides are
+    /// (otherwise the other branch alone satisfies the expression without
+    /// `id`). Ignores a trailing legacy `+`.
+    pub fn requires_license_id(&self, id: &str) -> bool {
+        match self {
+            Expression::License { id: this_id, .. } => {
+                this_id == id || this_id.trim_end_matches('+') == id
+            }
+            Expression::And(left, right) => {
+                left.requires_license_id(id) || right.requires_license_id(id)
+            }
+            Expression::Or(left, right) => {
+                left.requires_license_id(id) && right.requires_license_id(id)
+            }
+        }
+    }
+
+    /// Whether this expression is satisfiable under the given allow/deny
+    /// sets: `AND` requires both sides to be satisfiable, `OR` only one. An
+    /// empty `allow` set means "no allowlist restriction" rather than
+    /// "nothing is allowed", so `--deny` alone still works as a blocklist.
+    pub fn satisfied_by(&self, allow: &BTreeSet<String>, deny: &BTreeSet<String>) -> bool {
+        match self {
+            Expression::License { id, .. } => {
+                let id = id.trim_end_matches('+');
+                (allow.is_empty() || allow.contains(id)) && !deny.contains(id)
+            }
+            Expression::And(left, right) => {
+                left.satisfied_by(allow, deny) && right.satisfied_by(allow, deny)
+            }
+            Expression::Or(left, right) => {
+                left.satisfied_by(allow, deny) || right.satisfied_by(allow, deny)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnexpectedToken(String),
+    UnexpectedEnd,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedToken(token) => write!(f, "unexpected token `{}`", token),
+            ParseError::UnexpectedEnd => write!(f, "unexpected end of license expression"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Id(String),
+    And,
+    Or,
+    With,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    // The legacy `/` separator is equivalent to `OR` (e.g. `MIT/Apache-2.0`).
+    let normalized = input.replace('/', " OR ");
+
+    let mut tokens = Vec::new();
+    let mut chars = normalized.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            _ => {
+                let mut ident = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    ident.push(c);
+                    chars.next();
+                }
+                match ident.as_str() {
+                    "AND" => tokens.push(Token::And),
+                    "OR" => tokens.push(Token::Or),
+                    "WITH" => tokens.push(Token::With),
+                    "" => return Err(ParseError::UnexpectedToken(c.to_string())),
+                    _ => tokens.push(Token::Id(ident)),
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // `or-expression ::= and-expression ('OR' and-expression)*`
+    fn parse_or(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_and()?;
+        while let Some(Token::Or) = self.peek() {
+            self.bump();
+            let right = self.parse_and()?;
+            left = Expression::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    // `and-expression ::= unary ('AND' unary)*`, where
+    // `unary ::= '(' or-expression ')' | leaf`
+    fn parse_and(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_unary()?;
+        while let Some(Token::And) = self.peek() {
+            self.bump();
+            let right = self.parse_unary()?;
+            left = Expression::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, ParseError> {
+        match self.peek() {
+            Some(Token::LParen) => {
+                self.bump();
+                let inner = self.parse_or()?;
+                match self.bump() {
+                    Some(Token::RParen) => Ok(inner),
+                    Some(other) => Err(ParseError::UnexpectedToken(format!("{:?}", other))),
+                    None => Err(ParseError::UnexpectedEnd),
+                }
+            }
+            _ => self.parse_leaf(),
+        }
+    }
+
+    // `leaf ::= license-id ['+'] ['WITH' exception-id]`
+    //
+    // The legacy trailing `+` ("this version or any later version") is kept
+    // as-is on a single leaf rather than expanded into an `OR` of two ids:
+    // `+` isn't a choice between `GPL-3.0` and `GPL-3.0+`, it's a single
+    // open-ended license. `requires_license_id`/`satisfied_by` already treat
+    // a `+`-suffixed id as matching its bare id for lookups.
+    fn parse_leaf(&mut self) -> Result<Expression, ParseError> {
+        let id = match self.bump() {
+            Some(Token::Id(id)) => id,
+            Some(other) => return Err(ParseError::UnexpectedToken(format!("{:?}", other))),
+            None => return Err(ParseError::UnexpectedEnd),
+        };
+
+        let exception = if let Some(Token::With) = self.peek() {
+            self.bump();
+            match self.bump() {
+                Some(Token::Id(exception)) => Some(exception),
+                Some(other) => return Err(ParseError::UnexpectedToken(format!("{:?}", other))),
+                None => return Err(ParseError::UnexpectedEnd),
+            }
+        } else {
+            None
+        };
+
+        // Ids are kept as leaves whether or not they're in our curated id
+        // list below: the list is only used to offer OSI-approved shortcuts
+        // and is far from exhaustive, so treating an unrecognized id as a
+        // parse failure would collapse otherwise well-formed expressions
+        // (e.g. one clause using a niche id like `Unicode-3.0`) into a single
+        // opaque leaf, corrupting every downstream per-leaf check.
+        Ok(Expression::License { id, exception })
+    }
+}
+
+/// Parse an SPDX license expression, e.g. `(MIT OR Apache-2.0) AND BSD-3-Clause`
+/// or `GPL-2.0-only WITH Classpath-exception-2.0`.
+pub fn parse_expression(input: &str) -> Result<Expression, ParseError> {
+    let tokens = tokenize(input.trim())?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expression = parser.parse_or()?;
+    if parser.pos != parser.tokens.len() {
+        return Err(ParseError::UnexpectedToken(format!(
+            "{:?}",
+            parser.tokens[parser.pos]
+        )));
+    }
+    Ok(expression)
+}
+
+/// A curated subset of the SPDX license list that are OSI-approved, used to
+/// back `--allow-osi-only`. Best-effort and not exhaustive; consult
+/// <https://opensource.org/licenses> for authoritative status.
+pub static OSI_APPROVED_IDS: &[&str] = &[
+    "0BSD",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "Apache-2.0",
+    "Artistic-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSL-1.0",
+    "EPL-2.0",
+    "EUPL-1.2",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "ISC",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "MIT",
+    "MPL-2.0",
+    "OFL-1.1",
+    "Python-2.0",
+    "Unlicense",
+    "Zlib",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Expression {
+        parse_expression(input).unwrap_or_else(|err| panic!("{}: {}", input, err))
+    }
+
+    #[test]
+    fn parses_single_id() {
+        assert_eq!(
+            parse("MIT"),
+            Expression::License {
+                id: "MIT".to_owned(),
+                exception: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_with_clause() {
+        assert_eq!(
+            parse("GPL-2.0-only WITH Classpath-exception-2.0"),
+            Expression::License {
+                id: "GPL-2.0-only".to_owned(),
+                exception: Some("Classpath-exception-2.0".to_owned()),
+            }
+        );
+    }
+
+    #[test]
+    fn legacy_slash_normalizes_to_or() {
+        assert_eq!(parse("MIT/Apache-2.0"), parse("MIT OR Apache-2.0"));
+    }
+
+    #[test]
+    fn trailing_plus_stays_a_single_leaf() {
+        assert_eq!(
+            parse("GPL-3.0+"),
+            Expression::License {
+                id: "GPL-3.0+".to_owned(),
+                exception: None,
+            }
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        // `MIT AND BSD-3-Clause OR Apache-2.0` should parse as
+        // `(MIT AND BSD-3-Clause) OR Apache-2.0`, not the other grouping.
+        let expected = Expression::Or(
+            Box::new(Expression::And(
+                Box::new(Expression::License {
+                    id: "MIT".to_owned(),
+                    exception: None,
+                }),
+                Box::new(Expression::License {
+                    id: "BSD-3-Clause".to_owned(),
+                    exception: None,
+                }),
+            )),
+            Box::new(Expression::License {
+                id: "Apache-2.0".to_owned(),
+                exception: None,
+            }),
+        );
+        assert_eq!(parse("MIT AND BSD-3-Clause OR Apache-2.0"), expected);
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        assert_eq!(
+            parse("(MIT OR Apache-2.0) AND BSD-3-Clause"),
+            Expression::And(
+                Box::new(Expression::Or(
+                    Box::new(Expression::License {
+                        id: "MIT".to_owned(),
+                        exception: None,
+                    }),
+                    Box::new(Expression::License {
+                        id: "Apache-2.0".to_owned(),
+                        exception: None,
+                    }),
+                )),
+                Box::new(Expression::License {
+                    id: "BSD-3-Clause".to_owned(),
+                    exception: None,
+                }),
+            )
+        );
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        for input in [
+            "MIT",
+            "MIT OR Apache-2.0",
+            "MIT AND BSD-3-Clause",
+            "(MIT OR Apache-2.0) AND BSD-3-Clause",
+            "GPL-2.0-only WITH Classpath-exception-2.0",
+            "GPL-3.0+",
+        ] {
+            let expression = parse(input);
+            let rendered = expression.to_string();
+            assert_eq!(
+                parse(&rendered),
+                expression,
+                "re-parsing the Display of `{}` (rendered as `{}`) didn't round-trip",
+                input,
+                rendered
+            );
+        }
+    }
+
+    #[test]
+    fn unrecognized_id_still_parses_structurally() {
+        // Not in our curated id list, but still a well-formed expression.
+        assert_eq!(
+            parse("(MIT OR Apache-2.0) AND Unicode-3.0"),
+            Expression::And(
+                Box::new(Expression::Or(
+                    Box::new(Expression::License {
+                        id: "MIT".to_owned(),
+                        exception: None,
+                    }),
+                    Box::new(Expression::License {
+                        id: "Apache-2.0".to_owned(),
+                        exception: None,
+                    }),
+                )),
+                Box::new(Expression::License {
+                    id: "Unicode-3.0".to_owned(),
+                    exception: None,
+                }),
+            )
+        );
+    }
+
+    #[test]
+    fn requires_license_id_for_and_term() {
+        assert!(parse("Apache-2.0 AND MIT").requires_license_id("Apache-2.0"));
+    }
+
+    #[test]
+    fn does_not_require_license_id_for_or_alternative() {
+        assert!(!parse("MIT OR Apache-2.0").requires_license_id("Apache-2.0"));
+    }
+
+    #[test]
+    fn requires_license_id_when_or_is_unanimous() {
+        // Every branch mentions Apache-2.0 (combined with different
+        // exceptions), so it's mandatory even though the top-level op is OR.
+        assert!(
+            parse("Apache-2.0 OR Apache-2.0 WITH LLVM-exception").requires_license_id("Apache-2.0")
+        );
+    }
+
+    #[test]
+    fn mismatched_parens_fail_to_parse() {
+        assert!(parse_expression("(MIT OR Apache-2.0").is_err());
+    }
+}
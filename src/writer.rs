@@ -0,0 +1,315 @@
+//! Serializers for the resolved Bill-of-Materials.
+//!
+//! `real_main` resolves the dependency graph into a flat list of
+//! [`Component`]s and then hands them to whichever [`BomWriter`] the
+//! `--format` flag selected. Adding a new output dialect means adding a new
+//! `Format` variant and a matching `BomWriter` impl; the resolution logic in
+//! `main.rs` never needs to change.
+
+use std::io::{self, Write};
+use std::path;
+use std::str;
+
+use serde_json::json;
+
+/// A single resolved dependency, ready to be serialized.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Component {
+    pub name: String,
+    pub version: String,
+    pub licenses: String,
+    /// The license as a bare SPDX expression, e.g. `MIT OR Apache-2.0`, with
+    /// no provenance annotation. `None` when no SPDX expression was found
+    /// (the license was only found as a license file, the `license` field
+    /// didn't parse as an SPDX expression, or it is missing entirely) —
+    /// machine-readable formats must fall back to `NOASSERTION` rather than
+    /// serializing `licenses` verbatim.
+    pub license_expression: Option<String>,
+    pub license_files: Vec<path::PathBuf>,
+    /// Compliance observations about this component, e.g. a declared
+    /// Apache-2.0 license with no accompanying `NOTICE` file.
+    pub notes: Vec<String>,
+    /// Where this component's source comes from: the registry, a git
+    /// repository, or a local path.
+    pub source: String,
+    /// The dependency kinds (`normal`, `build`, `dev`) through which this
+    /// component enters the graph.
+    pub kinds: Vec<String>,
+}
+
+impl Component {
+    /// The package URL identifying this component, e.g. `pkg:cargo/serde@1.0.0`.
+    pub fn purl(&self) -> String {
+        format!("pkg:cargo/{}@{}", self.name, self.version)
+    }
+
+    fn kinds_joined(&self) -> String {
+        self.kinds.join("+")
+    }
+}
+
+/// The supported `--format` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The default human-readable table plus concatenated license texts.
+    Text,
+    /// A flat JSON array of
+    /// `{name, version, licenses, license_files, notes, source, kind}`.
+    Json,
+    /// An SPDX tag-value document.
+    Spdx,
+    /// A CycloneDX JSON BoM document.
+    CycloneDx,
+}
+
+impl str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "spdx" => Ok(Format::Spdx),
+            "cyclonedx" => Ok(Format::CycloneDx),
+            other => Err(format!(
+                "unknown format `{}` (expected one of: text, json, spdx, cyclonedx)",
+                other
+            )),
+        }
+    }
+}
+
+impl Format {
+    pub fn writer(self) -> Box<dyn BomWriter> {
+        match self {
+            Format::Text => Box::new(TextWriter),
+            Format::Json => Box::new(JsonWriter),
+            Format::Spdx => Box::new(SpdxWriter),
+            Format::CycloneDx => Box::new(CycloneDxWriter),
+        }
+    }
+}
+
+/// Serializes a resolved component list to an output stream.
+pub trait BomWriter {
+    fn write(&self, out: &mut dyn Write, components: &[Component]) -> io::Result<()>;
+}
+
+pub struct TextWriter;
+
+impl BomWriter for TextWriter {
+    fn write(&self, out: &mut dyn Write, components: &[Component]) -> io::Result<()> {
+        {
+            let mut tw = tabwriter::TabWriter::new(&mut *out);
+            writeln!(tw, "Name\t| Version\t| Licenses\t| Source\t| Kind")?;
+            writeln!(tw, "----\t| -------\t| --------\t| ------\t| ----")?;
+            for component in components {
+                writeln!(
+                    tw,
+                    "{}\t| {}\t| {}\t| {}\t| {}",
+                    &component.name,
+                    &component.version,
+                    &component.licenses,
+                    &component.source,
+                    component.kinds_joined(),
+                )?;
+            }
+
+            // TabWriter flush() makes the actual write to stdout.
+            tw.flush()?;
+        }
+
+        writeln!(out)?;
+        out.flush()?;
+
+        for component in components {
+            if component.license_files.is_empty() && component.notes.is_empty() {
+                continue;
+            }
+
+            writeln!(
+                out,
+                "-----BEGIN {} {} LICENSES-----",
+                component.name, component.version
+            )?;
+
+            for note in &component.notes {
+                writeln!(out, "NOTE: {}", note)?;
+            }
+
+            let mut buf = Vec::new();
+            let mut licenses_to_print = component.license_files.len();
+            for file in &component.license_files {
+                let mut fs = std::fs::File::open(file)?;
+                io::Read::read_to_end(&mut fs, &mut buf)?;
+                out.write_all(&buf)?;
+                buf.clear();
+                if licenses_to_print > 1 {
+                    out.write_all(b"\n-----NEXT LICENSE-----\n")?;
+                    licenses_to_print -= 1;
+                }
+            }
+
+            writeln!(
+                out,
+                "-----END {} {} LICENSES-----",
+                component.name, component.version
+            )?;
+            writeln!(out)?;
+        }
+
+        out.flush()
+    }
+}
+
+pub struct JsonWriter;
+
+impl BomWriter for JsonWriter {
+    fn write(&self, out: &mut dyn Write, components: &[Component]) -> io::Result<()> {
+        let value: Vec<_> = components
+            .iter()
+            .map(|component| {
+                json!({
+                    "name": component.name,
+                    "version": component.version,
+                    "licenses": component.licenses,
+                    "license_files": component
+                        .license_files
+                        .iter()
+                        .map(|path| path.to_string_lossy())
+                        .collect::<Vec<_>>(),
+                    "notes": component.notes,
+                    "source": component.source,
+                    "kind": component.kinds,
+                })
+            })
+            .collect();
+
+        serde_json::to_writer_pretty(&mut *out, &value)?;
+        writeln!(out)
+    }
+}
+
+pub struct SpdxWriter;
+
+impl BomWriter for SpdxWriter {
+    fn write(&self, out: &mut dyn Write, components: &[Component]) -> io::Result<()> {
+        writeln!(out, "SPDXVersion: SPDX-2.2")?;
+        writeln!(out, "DataLicense: CC0-1.0")?;
+        writeln!(out, "SPDXID: SPDXRef-DOCUMENT")?;
+        writeln!(out, "DocumentName: cargo-bom")?;
+        writeln!(
+            out,
+            "DocumentNamespace: https://spdx.org/spdxdocs/cargo-bom-{}",
+            now_rfc3339()
+        )?;
+        writeln!(out, "Creator: Tool: cargo-bom")?;
+        writeln!(out, "Created: {}", now_rfc3339())?;
+        writeln!(out)?;
+
+        for (index, component) in components.iter().enumerate() {
+            writeln!(out, "PackageName: {}", component.name)?;
+            writeln!(out, "SPDXID: SPDXRef-Package-{}", index)?;
+            writeln!(out, "PackageVersion: {}", component.version)?;
+            writeln!(out, "PackageDownloadLocation: {}", component.source)?;
+            writeln!(
+                out,
+                "PackageLicenseConcluded: {}",
+                component
+                    .license_expression
+                    .as_deref()
+                    .unwrap_or("NOASSERTION")
+            )?;
+            writeln!(out, "PackageLicenseDeclared: NOASSERTION")?;
+            writeln!(out, "PackageCopyrightText: NOASSERTION")?;
+            writeln!(
+                out,
+                "ExternalRef: PACKAGE-MANAGER purl {}",
+                component.purl()
+            )?;
+            writeln!(
+                out,
+                "PackageComment: dependency kind(s): {}",
+                component.kinds_joined()
+            )?;
+            writeln!(out)?;
+        }
+
+        out.flush()
+    }
+}
+
+pub struct CycloneDxWriter;
+
+impl BomWriter for CycloneDxWriter {
+    fn write(&self, out: &mut dyn Write, components: &[Component]) -> io::Result<()> {
+        let bom_components: Vec<_> = components
+            .iter()
+            .map(|component| {
+                json!({
+                    "type": "library",
+                    "bom-ref": component.purl(),
+                    "name": component.name,
+                    "version": component.version,
+                    "purl": component.purl(),
+                    "licenses": match &component.license_expression {
+                        Some(expression) => json!([{ "expression": expression }]),
+                        None => json!([]),
+                    },
+                    "properties": [
+                        { "name": "cargo:source", "value": component.source },
+                        { "name": "cargo:kind", "value": component.kinds_joined() },
+                    ],
+                })
+            })
+            .collect();
+
+        let document = json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.4",
+            "version": 1,
+            "metadata": {
+                "timestamp": now_rfc3339(),
+                "tools": [{ "name": "cargo-bom" }],
+            },
+            "components": bom_components,
+        });
+
+        serde_json::to_writer_pretty(&mut *out, &document)?;
+        writeln!(out)
+    }
+}
+
+fn now_rfc3339() -> String {
+    let duration = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = duration.as_secs();
+    let days = (secs / 86_400) as i64;
+    let time_of_day = secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    let hour = time_of_day / 3600;
+    let minute = (time_of_day % 3600) / 60;
+    let second = time_of_day % 60;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Converts a count of days since the Unix epoch into a `(year, month, day)`
+/// civil date. Adapted from Howard Hinnant's `civil_from_days` algorithm
+/// (<http://howardhinnant.github.io/date_algorithms.html>).
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = (z - era * 146_097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = if m <= 2 { y + 1 } else { y };
+    (y, m, d)
+}